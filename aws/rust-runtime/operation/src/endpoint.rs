@@ -7,100 +7,1167 @@ use crate::middleware::OperationMiddleware;
 use crate::Operation;
 use core::convert::AsRef;
 use http::uri::Uri;
+use http::HeaderMap;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
 use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread;
 
+/// A single typed value a rule can test or substitute into a template.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParamValue {
+    String(String),
+    Bool(bool),
+}
+
+impl ParamValue {
+    fn as_template_str(&self) -> String {
+        match self {
+            ParamValue::String(s) => s.clone(),
+            ParamValue::Bool(b) => b.to_string(),
+        }
+    }
+}
+
+impl From<&str> for ParamValue {
+    fn from(value: &str) -> Self {
+        ParamValue::String(value.to_string())
+    }
+}
+
+impl From<String> for ParamValue {
+    fn from(value: String) -> Self {
+        ParamValue::String(value)
+    }
+}
+
+impl From<bool> for ParamValue {
+    fn from(value: bool) -> Self {
+        ParamValue::Bool(value)
+    }
+}
+
+/// The inputs used to resolve an endpoint: region, FIPS/dual-stack flags, a
+/// custom endpoint override, and whatever arbitrary string/bool keys a
+/// particular service's rule set needs. Built via [`Params::builder`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EndpointParams {
+    values: HashMap<String, ParamValue>,
+}
+
+impl EndpointParams {
+    pub fn get(&self, key: &str) -> Option<&ParamValue> {
+        self.values.get(key)
+    }
+
+    pub fn region(&self) -> Option<&str> {
+        match self.get("region") {
+            Some(ParamValue::String(region)) => Some(region),
+            _ => None,
+        }
+    }
+
+    pub fn service(&self) -> Option<&str> {
+        match self.get("service") {
+            Some(ParamValue::String(service)) => Some(service),
+            _ => None,
+        }
+    }
+}
+
+/// Namespace for building an [`EndpointParams`].
+pub struct Params;
+
+impl Params {
+    pub fn builder() -> EndpointParamsBuilder {
+        EndpointParamsBuilder::default()
+    }
+}
+
+#[derive(Default)]
+pub struct EndpointParamsBuilder {
+    values: HashMap<String, ParamValue>,
+}
+
+impl EndpointParamsBuilder {
+    pub fn service(mut self, service: impl Into<String>) -> Self {
+        self.values
+            .insert("service".to_string(), ParamValue::String(service.into()));
+        self
+    }
+
+    pub fn region(mut self, region: impl Into<String>) -> Self {
+        self.values
+            .insert("region".to_string(), ParamValue::String(region.into()));
+        self
+    }
+
+    pub fn use_fips(mut self, use_fips: bool) -> Self {
+        self.values
+            .insert("UseFIPS".to_string(), ParamValue::Bool(use_fips));
+        self
+    }
+
+    pub fn use_dual_stack(mut self, use_dual_stack: bool) -> Self {
+        self.values
+            .insert("UseDualStack".to_string(), ParamValue::Bool(use_dual_stack));
+        self
+    }
+
+    pub fn endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.values
+            .insert("Endpoint".to_string(), ParamValue::String(endpoint.into()));
+        self
+    }
+
+    /// Sets an arbitrary, service-specific param.
+    pub fn set(mut self, key: impl Into<String>, value: impl Into<ParamValue>) -> Self {
+        self.values.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn build(self) -> EndpointParams {
+        EndpointParams {
+            values: self.values,
+        }
+    }
+}
+
+/// A resolved endpoint: a URI plus any headers or untyped properties the
+/// resolver wants downstream middleware to see (for example, an
+/// auth-scheme override carried alongside a regional endpoint).
+pub struct Endpoint {
+    uri: Uri,
+    headers: HeaderMap,
+    properties: PropertyBag,
+}
+
+impl Endpoint {
+    pub fn new(uri: Uri) -> Self {
+        Endpoint {
+            uri,
+            headers: HeaderMap::new(),
+            properties: PropertyBag::new(),
+        }
+    }
+
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn properties(&self) -> &PropertyBag {
+        &self.properties
+    }
+
+    /// Appends a header to be merged onto the outgoing request.
+    pub fn with_header(
+        mut self,
+        name: http::header::HeaderName,
+        value: http::header::HeaderValue,
+    ) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    /// Attaches an untyped property that downstream middleware can read back
+    /// by type out of the [`PropertyBag`] stored in the request's extensions.
+    pub fn with_property<T: Send + Sync + 'static>(mut self, value: T) -> Self {
+        self.properties.insert(value);
+        self
+    }
+}
+
+impl fmt::Debug for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Endpoint")
+            .field("uri", &self.uri)
+            .field("headers", &self.headers)
+            .finish()
+    }
+}
+
+/// An untyped bag of properties keyed by type, used to carry endpoint
+/// metadata (like an auth-scheme override) that doesn't fit in a URI or
+/// header, from endpoint resolution into downstream middleware.
+#[derive(Default)]
+pub struct PropertyBag {
+    map: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl PropertyBag {
+    pub fn new() -> Self {
+        PropertyBag {
+            map: HashMap::new(),
+        }
+    }
+
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|prev| prev.downcast::<T>().ok())
+            .map(|boxed| *boxed)
+    }
+
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.map
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref::<T>())
+    }
+}
+
+/// An error produced while resolving an endpoint.
+#[derive(Debug)]
+pub struct ResolveEndpointError {
+    message: String,
+}
+
+impl ResolveEndpointError {
+    pub fn message(message: impl Into<String>) -> Self {
+        ResolveEndpointError {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ResolveEndpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to resolve endpoint: {}", self.message)
+    }
+}
+
+impl Error for ResolveEndpointError {}
+
+/// The future returned by [`ResolveEndpoint::resolve_endpoint`].
+pub type EndpointFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<Endpoint, ResolveEndpointError>> + Send + 'a>>;
+
+/// Resolves an [`Endpoint`] for a request, asynchronously and fallibly.
+///
+/// Implementors may resolve the endpoint however they like: from a static
+/// configuration, a cache, or by evaluating a rule set against the request's
+/// inputs. Resolution can fail (for example, an invalid region or a rule set
+/// with no matching rule), so callers must handle `Err` rather than relying
+/// on the resolver to have panicked earlier.
+pub trait ResolveEndpoint: Send + Sync {
+    fn resolve_endpoint(&self, params: &EndpointParams) -> EndpointFuture<'_>;
+}
+
+#[derive(Debug)]
 pub struct StaticEndpoint(http::Uri);
 
 impl StaticEndpoint {
     pub fn uri(&self) -> &Uri {
         &self.0
     }
-    pub fn from_service_region(svc: impl AsRef<str>, region: impl AsRef<str>) -> Self {
-        StaticEndpoint(
-            Uri::from_str(&format!(
-                "https://{}.{}.amazonaws.com",
-                svc.as_ref(),
-                region.as_ref()
-            ))
-            .unwrap(),
-        )
+
+    /// Builds a `StaticEndpoint` from a service and region, validating the
+    /// resulting URI instead of panicking on a malformed input.
+    pub fn from_service_region(
+        svc: impl AsRef<str>,
+        region: impl AsRef<str>,
+    ) -> Result<Self, ResolveEndpointError> {
+        Self::from_service_region_with_variants(svc, region, false, false)
+    }
+
+    /// Builds a `StaticEndpoint` from a service and region, selecting the
+    /// partition (standard AWS, GovCloud, China, ISO, ...) from the region
+    /// prefix and, within it, the FIPS and/or dual-stack hostname variant.
+    /// Fails rather than emitting an unreachable host when the requested
+    /// variant isn't available in the resolved partition.
+    pub fn from_service_region_with_variants(
+        svc: impl AsRef<str>,
+        region: impl AsRef<str>,
+        use_fips: bool,
+        use_dual_stack: bool,
+    ) -> Result<Self, ResolveEndpointError> {
+        let svc = svc.as_ref();
+        let region = region.as_ref();
+        let partition = Partition::for_region(region);
+
+        if use_fips && !partition.supports_fips {
+            return Err(ResolveEndpointError::message(format!(
+                "FIPS endpoints are not available for region `{}`",
+                region
+            )));
+        }
+        let dns_suffix = if use_dual_stack {
+            partition.dual_stack_dns_suffix.ok_or_else(|| {
+                ResolveEndpointError::message(format!(
+                    "dual-stack endpoints are not available for region `{}`",
+                    region
+                ))
+            })?
+        } else {
+            partition.dns_suffix
+        };
+
+        let host = if use_fips {
+            format!("{}-fips.{}.{}", svc, region, dns_suffix)
+        } else {
+            format!("{}.{}.{}", svc, region, dns_suffix)
+        };
+
+        Uri::from_str(&format!("https://{}", host))
+            .map(StaticEndpoint)
+            .map_err(|err| ResolveEndpointError::message(format!("invalid endpoint uri: {}", err)))
     }
 
-    pub fn from_uri(uri: Uri) -> Self {
-        StaticEndpoint(uri)
+    /// Wraps `uri` as a `StaticEndpoint`, failing if it isn't absolute (i.e.
+    /// missing a scheme or authority), since a relative endpoint can never
+    /// be merged into a request's own URI.
+    pub fn from_uri(uri: Uri) -> Result<Self, ResolveEndpointError> {
+        if uri.scheme().is_none() || uri.authority().is_none() {
+            return Err(ResolveEndpointError::message(format!(
+                "endpoint uri `{}` is not absolute (missing scheme or authority)",
+                uri
+            )));
+        }
+        Ok(StaticEndpoint(uri))
     }
 
     pub fn apply(&self, base_uri: &Uri) -> Uri {
-        let parts = self.0.clone().into_parts();
+        merge_endpoint_into_request_uri(&self.0, base_uri)
+            .expect("StaticEndpoint's uri is validated absolute at construction")
+    }
+}
 
-        Uri::builder()
-            .authority(parts.authority.expect("base uri must have an authority"))
-            .scheme(parts.scheme.expect("base uri must have scheme"))
-            .path_and_query(base_uri.path_and_query().unwrap().clone())
-            .build()
-            .expect("valid uri")
+/// Combines a resolved endpoint's scheme and authority with a request's own
+/// path and query (treating the endpoint's path as a prefix, and merging
+/// rather than dropping either side's query), so that resolving a new
+/// endpoint never discards where the request was actually headed. Fails
+/// rather than panicking if `endpoint_uri` turns out not to be absolute.
+fn merge_endpoint_into_request_uri(
+    endpoint_uri: &Uri,
+    base_uri: &Uri,
+) -> Result<Uri, ResolveEndpointError> {
+    let parts = endpoint_uri.clone().into_parts();
+    let authority = parts.authority.ok_or_else(|| {
+        ResolveEndpointError::message(format!(
+            "resolved endpoint uri `{}` has no authority",
+            endpoint_uri
+        ))
+    })?;
+    let scheme = parts.scheme.ok_or_else(|| {
+        ResolveEndpointError::message(format!(
+            "resolved endpoint uri `{}` has no scheme",
+            endpoint_uri
+        ))
+    })?;
+    let endpoint_path_and_query = parts.path_and_query;
+    let request_path_and_query = base_uri.path_and_query();
+
+    Uri::builder()
+        .authority(authority)
+        .scheme(scheme)
+        .path_and_query(merge_path_and_query(
+            endpoint_path_and_query.as_ref(),
+            request_path_and_query,
+        ))
+        .build()
+        .map_err(|err| ResolveEndpointError::message(format!("invalid merged uri: {}", err)))
+}
+
+/// The DNS suffix and supported variants for a region partition.
+struct Partition {
+    dns_suffix: &'static str,
+    dual_stack_dns_suffix: Option<&'static str>,
+    supports_fips: bool,
+}
+
+/// Region-prefix -> partition table. The longest matching prefix wins; the
+/// empty prefix (standard AWS) is never matched and is the default.
+const PARTITIONS: &[(&str, Partition)] = &[
+    (
+        "us-gov-",
+        Partition {
+            dns_suffix: "amazonaws.com",
+            dual_stack_dns_suffix: None,
+            supports_fips: true,
+        },
+    ),
+    (
+        "cn-",
+        Partition {
+            dns_suffix: "amazonaws.com.cn",
+            dual_stack_dns_suffix: None,
+            supports_fips: false,
+        },
+    ),
+    (
+        "us-iso-",
+        Partition {
+            dns_suffix: "c2s.ic.gov",
+            dual_stack_dns_suffix: None,
+            supports_fips: true,
+        },
+    ),
+    (
+        "us-isob-",
+        Partition {
+            dns_suffix: "sc2s.sgov.gov",
+            dual_stack_dns_suffix: None,
+            supports_fips: true,
+        },
+    ),
+    (
+        "",
+        Partition {
+            dns_suffix: "amazonaws.com",
+            dual_stack_dns_suffix: Some("api.aws"),
+            supports_fips: true,
+        },
+    ),
+];
+
+impl Partition {
+    /// Looks up the partition a region belongs to by its longest matching
+    /// prefix, falling back to the standard AWS partition.
+    fn for_region(region: &str) -> &'static Partition {
+        PARTITIONS
+            .iter()
+            .filter(|(prefix, _)| !prefix.is_empty() && region.starts_with(prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, partition)| partition)
+            .unwrap_or(&PARTITIONS[PARTITIONS.len() - 1].1)
     }
 }
 
+/// Joins the endpoint's path (treated as a prefix when non-root) with the
+/// request's path, and merges their query strings rather than letting the
+/// request's query silently drop the endpoint's.
+fn merge_path_and_query(
+    endpoint: Option<&http::uri::PathAndQuery>,
+    request: Option<&http::uri::PathAndQuery>,
+) -> String {
+    let endpoint_path = endpoint.map(|pq| pq.path()).unwrap_or("/");
+    let request_path = request.map(|pq| pq.path()).unwrap_or("/");
+
+    let path = if endpoint_path.is_empty() || endpoint_path == "/" {
+        request_path.to_string()
+    } else {
+        format!(
+            "{}/{}",
+            endpoint_path.trim_end_matches('/'),
+            request_path.trim_start_matches('/')
+        )
+    };
+
+    let query = match (
+        endpoint.and_then(|pq| pq.query()),
+        request.and_then(|pq| pq.query()),
+    ) {
+        (Some(endpoint_query), Some(request_query)) => {
+            Some(format!("{}&{}", endpoint_query, request_query))
+        }
+        (Some(query), None) | (None, Some(query)) => Some(query.to_string()),
+        (None, None) => None,
+    };
+
+    match query {
+        Some(query) => format!("{}?{}", path, query),
+        None => path,
+    }
+}
+
+impl ResolveEndpoint for StaticEndpoint {
+    fn resolve_endpoint(&self, _params: &EndpointParams) -> EndpointFuture<'_> {
+        Box::pin(std::future::ready(Ok(Endpoint::new(self.0.clone()))))
+    }
+}
+
+/// A boolean test a [`Rule`]'s conditions are made of.
+#[derive(Clone, Debug)]
+pub enum Condition {
+    /// The param is present, regardless of value.
+    IsSet(String),
+    /// The param is present and equal to `value`.
+    Equals(String, ParamValue),
+    /// The param is a string containing `needle` (e.g. a partition or
+    /// region-prefix lookup).
+    Contains(String, String),
+}
+
+impl Condition {
+    fn is_satisfied(&self, params: &EndpointParams) -> bool {
+        match self {
+            Condition::IsSet(key) => params.get(key).is_some(),
+            Condition::Equals(key, value) => params.get(key) == Some(value),
+            Condition::Contains(key, needle) => {
+                matches!(params.get(key), Some(ParamValue::String(s)) if s.contains(needle.as_str()))
+            }
+        }
+    }
+}
+
+/// One entry in a [`Rules`] set: on the first rule whose conditions all
+/// pass, evaluation stops and that rule decides the outcome.
+#[derive(Clone, Debug)]
+pub enum Rule {
+    /// Produces an endpoint by substituting params into `template`.
+    Endpoint {
+        conditions: Vec<Condition>,
+        template: String,
+    },
+    /// Descends into nested rules when its own conditions pass.
+    Tree {
+        conditions: Vec<Condition>,
+        rules: Vec<Rule>,
+    },
+    /// Fails resolution with `message` when its conditions pass.
+    Error {
+        conditions: Vec<Condition>,
+        message: String,
+    },
+}
+
+/// A data-driven, ordered set of endpoint rules, evaluated against an
+/// [`EndpointParams`] to produce an [`Endpoint`].
+///
+/// This is what SDK codegen populates per service instead of the single
+/// hardcoded template `StaticEndpoint` used to bake in; a `StaticEndpoint`
+/// is just the degenerate case of a `Rules` with one unconditional rule.
+#[derive(Clone, Debug, Default)]
+pub struct Rules(Vec<Rule>);
+
+impl Rules {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Rules(rules)
+    }
+
+    /// A single, unconditional rule producing `template` — the rule-set
+    /// equivalent of a `StaticEndpoint`.
+    pub fn static_endpoint(template: impl Into<String>) -> Self {
+        Rules(vec![Rule::Endpoint {
+            conditions: vec![],
+            template: template.into(),
+        }])
+    }
+
+    pub fn resolve(&self, params: &EndpointParams) -> Result<Endpoint, ResolveEndpointError> {
+        Self::evaluate(&self.0, params)
+    }
+
+    fn evaluate(rules: &[Rule], params: &EndpointParams) -> Result<Endpoint, ResolveEndpointError> {
+        for rule in rules {
+            match rule {
+                Rule::Endpoint {
+                    conditions,
+                    template,
+                } => {
+                    if conditions.iter().all(|c| c.is_satisfied(params)) {
+                        return substitute_template(template, params).map(Endpoint::new);
+                    }
+                }
+                Rule::Tree { conditions, rules } => {
+                    if conditions.iter().all(|c| c.is_satisfied(params)) {
+                        return Self::evaluate(rules, params);
+                    }
+                }
+                Rule::Error {
+                    conditions,
+                    message,
+                } => {
+                    if conditions.iter().all(|c| c.is_satisfied(params)) {
+                        return Err(ResolveEndpointError::message(message.clone()));
+                    }
+                }
+            }
+        }
+        Err(ResolveEndpointError::message(format!(
+            "no rule matched params: {:?}",
+            params
+        )))
+    }
+}
+
+impl ResolveEndpoint for Rules {
+    fn resolve_endpoint(&self, params: &EndpointParams) -> EndpointFuture<'_> {
+        Box::pin(std::future::ready(self.resolve(params)))
+    }
+}
+
+/// Substitutes `{param}` placeholders in `template` with values out of
+/// `params`, failing with the list of params the template needed but
+/// weren't set.
+fn substitute_template(
+    template: &str,
+    params: &EndpointParams,
+) -> Result<Uri, ResolveEndpointError> {
+    let mut missing = Vec::new();
+    let mut result = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '{' {
+            result.push(c);
+            continue;
+        }
+        let mut key = String::new();
+        for c in chars.by_ref() {
+            if c == '}' {
+                break;
+            }
+            key.push(c);
+        }
+        match params.get(&key) {
+            Some(value) => result.push_str(&value.as_template_str()),
+            None => missing.push(key),
+        }
+    }
+    if !missing.is_empty() {
+        return Err(ResolveEndpointError::message(format!(
+            "missing required params for endpoint template: {}",
+            missing.join(", ")
+        )));
+    }
+    let uri = Uri::from_str(&result).map_err(|err| {
+        ResolveEndpointError::message(format!("invalid endpoint uri `{}`: {}", result, err))
+    })?;
+    if uri.scheme().is_none() || uri.authority().is_none() {
+        return Err(ResolveEndpointError::message(format!(
+            "endpoint template produced `{}`, which is not an absolute uri \
+             (missing scheme or authority)",
+            result
+        )));
+    }
+    Ok(uri)
+}
+
 pub trait ProvideEndpoint {
-    fn set_endpoint(&self, request_uri: &mut Uri);
+    /// Resolves the `Endpoint` to apply against `request_uri`, headers and
+    /// properties included, rather than just mutating the URI in place.
+    fn provide_endpoint(&self, request_uri: &Uri) -> Endpoint;
 }
 
 impl ProvideEndpoint for StaticEndpoint {
-    fn set_endpoint(&self, request_uri: &mut Uri) {
-        let new_uri = self.apply(request_uri);
-        *request_uri = new_uri;
+    fn provide_endpoint(&self, request_uri: &Uri) -> Endpoint {
+        Endpoint::new(self.apply(request_uri))
     }
 }
 
+/// Sets `request`'s URI to `endpoint`'s, merging (not clobbering) the
+/// endpoint's headers into the request and stashing its properties in the
+/// request's extensions for downstream middleware to read back out.
+fn apply_endpoint<H>(request: &mut Operation<H>, endpoint: Endpoint) {
+    let Endpoint {
+        uri,
+        headers,
+        properties,
+    } = endpoint;
+    *request.base.uri_mut() = uri;
+    for (name, value) in headers.iter() {
+        request.base.headers_mut().append(name, value.clone());
+    }
+    request.base.extensions_mut().insert(properties);
+}
+
 impl<H, T> OperationMiddleware<H> for T
 where
     T: ProvideEndpoint,
 {
     fn apply(&self, request: &mut Operation<H>) -> Result<(), Box<dyn Error>> {
-        self.set_endpoint(&mut request.base.uri_mut());
+        let endpoint = self.provide_endpoint(request.base.uri());
+        apply_endpoint(request, endpoint);
         Ok(())
     }
 }
 
+/// Wakes the thread `block_on` parked on, rather than spinning, when the
+/// future it's driving becomes ready again.
+struct ThreadWaker(thread::Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Polls `future` to completion without a real reactor, parking the current
+/// thread between polls instead of spinning. Suitable for resolvers that
+/// park waiting on I/O (a cache miss, a network call) as well as ones that
+/// complete promptly (a static endpoint, a warm cache).
+fn block_on<F: Future>(mut future: F) -> F::Output {
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    // SAFETY: `future` is not moved after this point.
+    let mut future = unsafe { Pin::new_unchecked(&mut future) };
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        thread::park();
+    }
+}
+
+/// A validated host-label prefix applied ahead of an endpoint's authority.
+///
+/// This corresponds to the Smithy `endpoint`/`hostPrefix` trait, where an
+/// operation declares a label (e.g. `{accountId}.`) to be prepended to the
+/// service's authority rather than baked into the path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EndpointPrefix(String);
+
+impl EndpointPrefix {
+    /// Validates `prefix` as a host label and wraps it.
+    ///
+    /// `prefix` is concatenated directly onto the front of the authority's
+    /// host with no separator inserted, so it must supply its own trailing
+    /// `-` or `.` (e.g. `data-`, `123456789012.`) if it wants one. Only ASCII
+    /// letters, digits, `-` and `.` are accepted, so a `{label}` placeholder
+    /// has to be substituted before it gets here. What it
+    /// must not do is start with `-` or `.` (which would corrupt the host's
+    /// first label) or contain `..` (which would produce an empty label) —
+    /// `http::uri::Authority` parses all of these without complaint, so they
+    /// have to be rejected here instead.
+    pub fn new(prefix: impl Into<String>) -> Result<Self, ResolveEndpointError> {
+        let prefix = prefix.into();
+        let is_valid_label = !prefix.is_empty()
+            && !prefix.starts_with(['-', '.'])
+            && !prefix.contains("..")
+            && prefix
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+        if !is_valid_label {
+            return Err(ResolveEndpointError::message(format!(
+                "`{}` is not a valid host label prefix",
+                prefix
+            )));
+        }
+        Ok(EndpointPrefix(prefix))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Prepends `prefix` to the host portion of `uri`'s authority, validating
+/// that the result is still a syntactically valid authority.
+fn prefix_authority(uri: &Uri, prefix: &EndpointPrefix) -> Result<Uri, ResolveEndpointError> {
+    let parts = uri.clone().into_parts();
+    let authority = parts
+        .authority
+        .ok_or_else(|| ResolveEndpointError::message("uri has no authority to prefix"))?;
+    let prefixed = match authority.port() {
+        Some(port) => format!("{}{}:{}", prefix.as_str(), authority.host(), port),
+        None => format!("{}{}", prefix.as_str(), authority.host()),
+    };
+    let authority = http::uri::Authority::from_str(&prefixed).map_err(|err| {
+        ResolveEndpointError::message(format!("`{}` is not a valid authority: {}", prefixed, err))
+    })?;
+
+    Uri::builder()
+        .scheme(parts.scheme.expect("uri must have scheme"))
+        .authority(authority)
+        .path_and_query(parts.path_and_query.expect("uri must have path"))
+        .build()
+        .map_err(|err| ResolveEndpointError::message(format!("invalid uri: {}", err)))
+}
+
 // TODO: this should probably move to a collection of middlewares
-#[derive(Clone, Copy)]
-/// Set the endpoint for a request based on the endpoint config
-pub struct EndpointMiddleware;
+#[derive(Clone, Default)]
+/// Set the endpoint for a request based on the endpoint config, optionally
+/// prepending an [`EndpointPrefix`] (Smithy's `hostPrefix` trait) to the
+/// resolved authority.
+pub struct EndpointMiddleware {
+    prefix: Option<EndpointPrefix>,
+}
+
+impl EndpointMiddleware {
+    pub fn new() -> Self {
+        EndpointMiddleware { prefix: None }
+    }
+
+    pub fn with_prefix(prefix: EndpointPrefix) -> Self {
+        EndpointMiddleware {
+            prefix: Some(prefix),
+        }
+    }
+}
+
 impl<H> OperationMiddleware<H> for EndpointMiddleware {
     fn apply(&self, request: &mut Operation<H>) -> Result<(), Box<dyn Error>> {
-        let endpoint_config = &request.endpoint_config;
-        endpoint_config.set_endpoint(&mut request.base.uri_mut());
+        let mut endpoint = block_on(
+            request
+                .endpoint_config
+                .resolve_endpoint(&request.endpoint_params),
+        )?;
+        endpoint.uri = merge_endpoint_into_request_uri(&endpoint.uri, request.base.uri())?;
+        if let Some(prefix) = &self.prefix {
+            endpoint.uri = prefix_authority(&endpoint.uri, prefix)?;
+        }
+        apply_endpoint(request, endpoint);
         Ok(())
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::endpoint::StaticEndpoint;
+    use crate::endpoint::{
+        Endpoint, EndpointPrefix, Params, ResolveEndpoint, Rule, Rules, StaticEndpoint,
+    };
     use http::Uri;
     use std::str::FromStr;
 
     #[test]
     fn endpoint_from_svc() {
-        let endpoint = StaticEndpoint::from_service_region("dynamodb", "us-west-2");
+        let endpoint = StaticEndpoint::from_service_region("dynamodb", "us-west-2").unwrap();
         assert_eq!(
             endpoint.uri().to_string(),
             "https://dynamodb.us-west-2.amazonaws.com/"
         );
     }
 
+    #[test]
+    fn invalid_svc_does_not_panic() {
+        let err = StaticEndpoint::from_service_region("dynamodb", "us-west-2\n").unwrap_err();
+        assert!(err.to_string().contains("invalid endpoint uri"));
+    }
+
+    #[test]
+    fn govcloud_region_uses_govcloud_partition() {
+        let endpoint = StaticEndpoint::from_service_region("dynamodb", "us-gov-west-1").unwrap();
+        assert_eq!(
+            endpoint.uri().to_string(),
+            "https://dynamodb.us-gov-west-1.amazonaws.com/"
+        );
+    }
+
+    #[test]
+    fn china_region_uses_cn_suffix() {
+        let endpoint = StaticEndpoint::from_service_region("dynamodb", "cn-north-1").unwrap();
+        assert_eq!(
+            endpoint.uri().to_string(),
+            "https://dynamodb.cn-north-1.amazonaws.com.cn/"
+        );
+    }
+
+    #[test]
+    fn iso_and_isob_partitions_do_not_cross_match() {
+        // `us-isob-` does not start with `us-iso-`, so these two partitions
+        // are disjoint today, but `for_region` now picks the longest
+        // matching prefix rather than relying on table order, so this stays
+        // correct even if an overlapping prefix is added later.
+        let iso = StaticEndpoint::from_service_region("dynamodb", "us-iso-east-1").unwrap();
+        assert_eq!(
+            iso.uri().to_string(),
+            "https://dynamodb.us-iso-east-1.c2s.ic.gov/"
+        );
+
+        let isob = StaticEndpoint::from_service_region("dynamodb", "us-isob-east-1").unwrap();
+        assert_eq!(
+            isob.uri().to_string(),
+            "https://dynamodb.us-isob-east-1.sc2s.sgov.gov/"
+        );
+    }
+
+    #[test]
+    fn fips_variant_prepends_fips_to_service() {
+        let endpoint =
+            StaticEndpoint::from_service_region_with_variants("dynamodb", "us-west-2", true, false)
+                .unwrap();
+        assert_eq!(
+            endpoint.uri().to_string(),
+            "https://dynamodb-fips.us-west-2.amazonaws.com/"
+        );
+    }
+
+    #[test]
+    fn dual_stack_variant_uses_api_aws_suffix() {
+        let endpoint =
+            StaticEndpoint::from_service_region_with_variants("dynamodb", "us-west-2", false, true)
+                .unwrap();
+        assert_eq!(
+            endpoint.uri().to_string(),
+            "https://dynamodb.us-west-2.api.aws/"
+        );
+    }
+
+    #[test]
+    fn fips_is_rejected_in_china_partition() {
+        let err = StaticEndpoint::from_service_region_with_variants(
+            "dynamodb",
+            "cn-north-1",
+            true,
+            false,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("FIPS"));
+    }
+
+    #[test]
+    fn dual_stack_is_rejected_outside_standard_partition() {
+        let err = StaticEndpoint::from_service_region_with_variants(
+            "dynamodb",
+            "us-gov-west-1",
+            false,
+            true,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("dual-stack"));
+    }
+
     #[test]
     fn properly_update_uri() {
         let uri = Uri::builder()
             .path_and_query("/get?k=123&v=456")
             .build()
             .unwrap();
-        let endpoint = StaticEndpoint::from_uri(Uri::from_str("http://localhost:8080/").unwrap());
+        let endpoint =
+            StaticEndpoint::from_uri(Uri::from_str("http://localhost:8080/").unwrap()).unwrap();
         assert_eq!(
             endpoint.apply(&uri).to_string(),
             "http://localhost:8080/get?k=123&v=456"
         );
     }
+
+    #[test]
+    fn base_path_is_treated_as_a_prefix() {
+        let uri = Uri::builder().path_and_query("/get?k=1").build().unwrap();
+        let endpoint =
+            StaticEndpoint::from_uri(Uri::from_str("http://host/base").unwrap()).unwrap();
+        assert_eq!(endpoint.apply(&uri).to_string(), "http://host/base/get?k=1");
+    }
+
+    #[test]
+    fn endpoint_and_request_queries_are_merged() {
+        let uri = Uri::builder().path_and_query("/get?k=1").build().unwrap();
+        let endpoint =
+            StaticEndpoint::from_uri(Uri::from_str("http://host/base?shared=true").unwrap())
+                .unwrap();
+        assert_eq!(
+            endpoint.apply(&uri).to_string(),
+            "http://host/base/get?shared=true&k=1"
+        );
+    }
+
+    #[test]
+    fn root_endpoint_path_does_not_prefix() {
+        let uri = Uri::builder().path_and_query("/get?k=1").build().unwrap();
+        let endpoint = StaticEndpoint::from_uri(Uri::from_str("http://host/").unwrap()).unwrap();
+        assert_eq!(endpoint.apply(&uri).to_string(), "http://host/get?k=1");
+    }
+
+    #[test]
+    fn schemeless_endpoint_uri_is_rejected_instead_of_panicking() {
+        // `Uri::from_str("example.com")` parses to authority=Some,
+        // scheme=None; merging it in must fail, not panic.
+        let endpoint_uri = Uri::from_str("example.com").unwrap();
+        let request_uri = Uri::from_str("https://placeholder/get?k=1").unwrap();
+        let err = super::merge_endpoint_into_request_uri(&endpoint_uri, &request_uri).unwrap_err();
+        assert!(err.to_string().contains("no scheme"));
+    }
+
+    #[test]
+    fn from_uri_rejects_a_relative_uri() {
+        let err = StaticEndpoint::from_uri(Uri::from_str("/foo").unwrap()).unwrap_err();
+        assert!(err.to_string().contains("not absolute"));
+    }
+
+    #[test]
+    fn block_on_parks_until_woken_from_another_thread() {
+        use std::sync::{Arc, Mutex};
+        use std::task::Poll;
+        use std::time::Duration;
+
+        struct WakesLater(Arc<Mutex<bool>>);
+        impl std::future::Future for WakesLater {
+            type Output = u32;
+            fn poll(self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<u32> {
+                if *self.0.lock().unwrap() {
+                    Poll::Ready(42)
+                } else {
+                    let waker = cx.waker().clone();
+                    let ready = self.0.clone();
+                    std::thread::spawn(move || {
+                        std::thread::sleep(Duration::from_millis(20));
+                        *ready.lock().unwrap() = true;
+                        waker.wake();
+                    });
+                    Poll::Pending
+                }
+            }
+        }
+
+        let result = super::block_on(WakesLater(Arc::new(Mutex::new(false))));
+        assert_eq!(result, 42);
+    }
+
+    #[test]
+    fn prefix_is_prepended_to_authority_host() {
+        let uri = Uri::from_str("https://service.us-west-2.amazonaws.com/").unwrap();
+        let prefix = EndpointPrefix::new("data-").unwrap();
+        let prefixed = super::prefix_authority(&uri, &prefix).unwrap();
+        assert_eq!(
+            prefixed.to_string(),
+            "https://data-service.us-west-2.amazonaws.com/"
+        );
+    }
+
+    #[test]
+    fn invalid_prefix_label_is_rejected() {
+        let err = EndpointPrefix::new("not a label/").unwrap_err();
+        assert!(err.to_string().contains("not a valid host label prefix"));
+    }
+
+    #[test]
+    fn leading_dot_prefix_is_rejected() {
+        // `http::uri::Authority::from_str(".service.us-west-2.amazonaws.com")`
+        // parses without error, so this must be caught before it ever
+        // reaches `prefix_authority`.
+        assert!(EndpointPrefix::new(".").is_err());
+    }
+
+    #[test]
+    fn leading_hyphen_prefix_is_rejected() {
+        // `http::uri::Authority::from_str("-svc.x.com")` parses without
+        // error, so this must be caught before it ever reaches
+        // `prefix_authority`.
+        assert!(EndpointPrefix::new("-svc").is_err());
+    }
+
+    #[test]
+    fn consecutive_dots_in_prefix_are_rejected() {
+        // `http::uri::Authority::from_str("..x")` parses without error, so
+        // this must be caught before it ever reaches `prefix_authority`.
+        assert!(EndpointPrefix::new("..").is_err());
+    }
+
+    #[test]
+    fn endpoint_carries_headers_and_properties() {
+        struct AuthSchemeOverride(&'static str);
+
+        let endpoint = Endpoint::new(Uri::from_str("https://host/").unwrap())
+            .with_header(
+                http::header::HeaderName::from_static("x-amz-routing"),
+                http::header::HeaderValue::from_static("shard-1"),
+            )
+            .with_property(AuthSchemeOverride("sigv4a"));
+
+        assert_eq!(endpoint.headers().get("x-amz-routing").unwrap(), "shard-1");
+        assert_eq!(
+            endpoint.properties().get::<AuthSchemeOverride>().unwrap().0,
+            "sigv4a"
+        );
+    }
+
+    #[test]
+    fn resolves_static_endpoint_without_panicking() {
+        let endpoint = StaticEndpoint::from_service_region("dynamodb", "us-west-2").unwrap();
+        let params = Params::builder()
+            .service("dynamodb")
+            .region("us-west-2")
+            .build();
+        let resolved =
+            super::block_on(endpoint.resolve_endpoint(&params)).expect("resolution succeeds");
+        assert_eq!(
+            resolved.uri().to_string(),
+            "https://dynamodb.us-west-2.amazonaws.com/"
+        );
+    }
+
+    #[test]
+    fn rule_substitutes_params_into_template() {
+        let rules = Rules::new(vec![Rule::Endpoint {
+            conditions: vec![],
+            template: "https://{service}.{region}.amazonaws.com".to_string(),
+        }]);
+        let params = Params::builder()
+            .service("dynamodb")
+            .region("us-west-2")
+            .build();
+        let endpoint = rules.resolve(&params).unwrap();
+        assert_eq!(
+            endpoint.uri().to_string(),
+            "https://dynamodb.us-west-2.amazonaws.com/"
+        );
+    }
+
+    #[test]
+    fn rule_with_unmet_condition_falls_through() {
+        use crate::endpoint::Condition;
+
+        let rules = Rules::new(vec![
+            Rule::Endpoint {
+                conditions: vec![Condition::Equals("UseFIPS".to_string(), true.into())],
+                template: "https://{service}-fips.{region}.amazonaws.com".to_string(),
+            },
+            Rule::Endpoint {
+                conditions: vec![],
+                template: "https://{service}.{region}.amazonaws.com".to_string(),
+            },
+        ]);
+        let params = Params::builder()
+            .service("dynamodb")
+            .region("us-west-2")
+            .build();
+        let endpoint = rules.resolve(&params).unwrap();
+        assert_eq!(
+            endpoint.uri().to_string(),
+            "https://dynamodb.us-west-2.amazonaws.com/"
+        );
+    }
+
+    #[test]
+    fn missing_template_param_is_reported() {
+        let rules = Rules::new(vec![Rule::Endpoint {
+            conditions: vec![],
+            template: "https://{service}.{region}.amazonaws.com".to_string(),
+        }]);
+        let params = Params::builder().region("us-west-2").build();
+        let err = rules.resolve(&params).unwrap_err();
+        assert!(err.to_string().contains("service"));
+    }
+
+    #[test]
+    fn rule_template_missing_scheme_and_authority_is_rejected() {
+        // A template that substitutes down to e.g. `localhost:8080` (valid
+        // per `Uri::from_str`, but schemeless) must fail here rather than
+        // resolving "successfully" and only failing later when the
+        // middleware tries to merge it into the request's uri.
+        let rules = Rules::new(vec![Rule::Endpoint {
+            conditions: vec![],
+            template: "{host}".to_string(),
+        }]);
+        let params = Params::builder().set("host", "localhost:8080").build();
+        let err = rules.resolve(&params).unwrap_err();
+        assert!(err.to_string().contains("not an absolute uri"));
+    }
+
+    #[test]
+    fn rule_resolved_endpoint_merges_into_request_path() {
+        // `Rules` only implements `ResolveEndpoint`, so this drives it the
+        // same way `EndpointMiddleware::apply` does: behind a boxed trait
+        // object, via `block_on`, with the result merged into the request's
+        // own path and query.
+        let rules: Box<dyn ResolveEndpoint> = Box::new(Rules::new(vec![Rule::Endpoint {
+            conditions: vec![],
+            template: "https://{service}.{region}.amazonaws.com".to_string(),
+        }]));
+        let params = Params::builder()
+            .service("dynamodb")
+            .region("us-west-2")
+            .build();
+        let resolved = super::block_on(rules.resolve_endpoint(&params)).expect("resolves");
+        let request_uri = Uri::from_str("https://placeholder/tables/Foo").unwrap();
+        let merged =
+            super::merge_endpoint_into_request_uri(resolved.uri(), &request_uri).expect("merges");
+        assert_eq!(
+            merged.to_string(),
+            "https://dynamodb.us-west-2.amazonaws.com/tables/Foo"
+        );
+    }
 }